@@ -0,0 +1,82 @@
+use std::collections::HashSet;
+use std::hash::Hash;
+
+use chrono::{DateTime, Duration, Utc};
+
+// Metadata kept in a `RepostCache` is ordered and evicted by its timestamp.
+pub trait Timestamped {
+    fn timestamp(&self) -> DateTime<Utc>;
+}
+
+// A bounded, age-aware cache of repost metadata. Caps entries at `limit`,
+// evicting the oldest `timestamp` on overflow, and — when `max_age_days` is
+// set — drops entries past the window on access so stale posts are never
+// flagged as reposts.
+pub struct RepostCache<T> {
+    entries: HashSet<T>,
+    limit: u64,
+    max_age_days: Option<i64>,
+}
+
+impl<T: Eq + Hash + Clone + Timestamped> RepostCache<T> {
+    pub fn new(entries: HashSet<T>, limit: u64, max_age_days: Option<i64>) -> Self {
+        RepostCache { entries, limit, max_age_days }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        self.entries.iter()
+    }
+
+    pub fn find<F: Fn(&T) -> bool>(&self, predicate: F) -> Option<&T> {
+        self.entries.iter().find(|e| predicate(e))
+    }
+
+    pub fn retain<F: Fn(&T) -> bool>(&mut self, predicate: F) {
+        self.entries.retain(|e| predicate(e));
+    }
+
+    // Insert `value`, evicting the oldest entries to stay within `limit`.
+    // Returns the evicted entries so callers can mirror the delete to disk.
+    pub fn insert(&mut self, value: T) -> Vec<T> {
+        let mut evicted = Vec::new();
+        if self.limit > 0 {
+            while self.entries.len() as u64 >= self.limit {
+                match self.oldest() {
+                    Some(oldest) => {
+                        self.entries.remove(&oldest);
+                        evicted.push(oldest);
+                    }
+                    None => break,
+                }
+            }
+        }
+        self.entries.insert(value);
+        evicted
+    }
+
+    // Drop entries older than the configured window, returning the evicted
+    // set so callers can mirror the deletion to disk.
+    pub fn prune(&mut self) -> Vec<T> {
+        let max_age_days = match self.max_age_days {
+            Some(days) => days,
+            None => return Vec::new(),
+        };
+        let cutoff = Utc::now() - Duration::days(max_age_days);
+        let expired: Vec<T> = self.entries.iter()
+            .filter(|e| e.timestamp() < cutoff)
+            .cloned()
+            .collect();
+        for entry in &expired {
+            self.entries.remove(entry);
+        }
+        expired
+    }
+
+    fn oldest(&self) -> Option<T> {
+        self.entries.iter().min_by_key(|e| e.timestamp()).cloned()
+    }
+}
@@ -0,0 +1,128 @@
+use serenity::client::Context;
+use serenity::framework::standard::macros::{check, command, group};
+use serenity::framework::standard::{Args, CommandOptions, CommandResult, Reason};
+use serenity::model::channel::Message;
+use serenity::utils::MessageBuilder;
+
+use crate::{
+    forget_entry, set_allowed, unset_allowed, AllowedHashes, AllowedLinks, Config, HashCache,
+    LinkCache, Stats,
+};
+
+#[group]
+#[commands(stats, allow, unallow, forget, threshold)]
+struct Repost;
+
+// Destructive commands require the caller to be able to moderate the channel.
+#[check]
+#[name = "ManageMessages"]
+async fn manage_messages_check(
+    ctx: &Context,
+    msg: &Message,
+    _: &mut Args,
+    _: &CommandOptions,
+) -> Result<(), Reason> {
+    if let Ok(member) = msg.member(&ctx.http).await {
+        if let Ok(perms) = member.permissions(&ctx.cache) {
+            if perms.manage_messages() {
+                return Ok(());
+            }
+        }
+    }
+    Err(Reason::User(String::from("You need the Manage Messages permission to do that.")))
+}
+
+#[command]
+#[description = "Show cache occupancy and the number of reposts caught."]
+async fn stats(ctx: &Context, msg: &Message) -> CommandResult {
+    let data = ctx.data.read().await;
+    let images = data.get::<HashCache>().map(|c| c.len()).unwrap_or(0);
+    let links = data.get::<LinkCache>().map(|c| c.len()).unwrap_or(0);
+    let allowed_hashes = data.get::<AllowedHashes>().map(|c| c.len()).unwrap_or(0);
+    let allowed_links = data.get::<AllowedLinks>().map(|c| c.len()).unwrap_or(0);
+    let caught = data.get::<Stats>().copied().unwrap_or(0);
+    let content = MessageBuilder::new()
+        .push_line(format!("Cached images: {}", images))
+        .push_line(format!("Cached links: {}", links))
+        .push_line(format!("Allowed hashes: {}", allowed_hashes))
+        .push_line(format!("Allowed links: {}", allowed_links))
+        .push(format!("Reposts caught: {}", caught))
+        .build();
+    msg.reply(&ctx.http, content).await?;
+    Ok(())
+}
+
+#[command]
+#[checks(ManageMessages)]
+#[description = "Allow-list the attachments/embeds of the replied-to message."]
+async fn allow(ctx: &Context, msg: &Message) -> CommandResult {
+    match &msg.referenced_message {
+        Some(target) => {
+            set_allowed(ctx, target).await;
+            msg.reply(&ctx.http, "Allowed.").await?;
+        }
+        None => {
+            msg.reply(&ctx.http, "Reply to the message you want to allow.").await?;
+        }
+    }
+    Ok(())
+}
+
+#[command]
+#[checks(ManageMessages)]
+#[description = "Remove the replied-to message's attachments/embeds from the allow-list."]
+async fn unallow(ctx: &Context, msg: &Message) -> CommandResult {
+    match &msg.referenced_message {
+        Some(target) => {
+            unset_allowed(ctx, target).await;
+            msg.reply(&ctx.http, "Unallowed.").await?;
+        }
+        None => {
+            msg.reply(&ctx.http, "Reply to the message you want to unallow.").await?;
+        }
+    }
+    Ok(())
+}
+
+#[command]
+#[checks(ManageMessages)]
+#[description = "Evict the cached entry first seen at the given message link."]
+async fn forget(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    let link = args.rest().trim();
+    if link.is_empty() {
+        msg.reply(&ctx.http, "Usage: forget <msg_link>").await?;
+        return Ok(());
+    }
+    if forget_entry(ctx, link).await {
+        msg.reply(&ctx.http, "Forgotten.").await?;
+    } else {
+        msg.reply(&ctx.http, "Nothing cached for that message link.").await?;
+    }
+    Ok(())
+}
+
+#[command]
+#[checks(ManageMessages)]
+#[description = "View or set the perceptual-hash match distance at runtime."]
+async fn threshold(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    let arg = args.rest().trim();
+    if arg.is_empty() {
+        let data = ctx.data.read().await;
+        let current = data.get::<Config>().map(|c| c.threshold).unwrap_or(0);
+        msg.reply(&ctx.http, format!("Current match distance: {}", current)).await?;
+        return Ok(());
+    }
+    match arg.parse::<u32>() {
+        Ok(value) => {
+            let mut data = ctx.data.write().await;
+            if let Some(config) = data.get_mut::<Config>() {
+                config.threshold = value;
+            }
+            msg.reply(&ctx.http, format!("Match distance set to {}.", value)).await?;
+        }
+        Err(_) => {
+            msg.reply(&ctx.http, "Match distance must be a non-negative integer.").await?;
+        }
+    }
+    Ok(())
+}
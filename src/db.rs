@@ -0,0 +1,100 @@
+use std::collections::HashSet;
+use std::env;
+
+use img_hash::ImageHash;
+use sled::Db;
+
+use crate::{ImageMetadata, LinkMetadata};
+
+pub const IMAGE_TREE: &str = "images";
+pub const LINK_TREE: &str = "links";
+pub const ALLOWED_HASH_TREE: &str = "allowed_hashes";
+pub const ALLOWED_LINK_TREE: &str = "allowed_links";
+
+// Open (creating if necessary) the sled database backing the repost caches.
+pub fn open() -> Db {
+    let path = env::var("REPOST_DB_PATH").expect("Expected a REPOST_DB_PATH env var");
+    sled::open(path).expect("Error opening repost database")
+}
+
+pub fn load_images(db: &Db) -> HashSet<ImageMetadata> {
+    let tree = db.open_tree(IMAGE_TREE).expect("Error opening image tree");
+    tree.iter()
+        .values()
+        .filter_map(Result::ok)
+        .filter_map(|v| bincode::deserialize::<ImageMetadata>(&v).ok())
+        .collect()
+}
+
+pub fn load_links(db: &Db) -> HashSet<LinkMetadata> {
+    let tree = db.open_tree(LINK_TREE).expect("Error opening link tree");
+    tree.iter()
+        .values()
+        .filter_map(Result::ok)
+        .filter_map(|v| bincode::deserialize::<LinkMetadata>(&v).ok())
+        .collect()
+}
+
+pub fn load_allowed_hashes(db: &Db) -> HashSet<ImageHash> {
+    let tree = db.open_tree(ALLOWED_HASH_TREE).expect("Error opening allowed hash tree");
+    tree.iter()
+        .keys()
+        .filter_map(Result::ok)
+        .filter_map(|k| String::from_utf8(k.to_vec()).ok())
+        .filter_map(|b64| ImageHash::from_base64(&b64).ok())
+        .collect()
+}
+
+pub fn load_allowed_links(db: &Db) -> HashSet<String> {
+    let tree = db.open_tree(ALLOWED_LINK_TREE).expect("Error opening allowed link tree");
+    tree.iter()
+        .keys()
+        .filter_map(Result::ok)
+        .filter_map(|k| String::from_utf8(k.to_vec()).ok())
+        .collect()
+}
+
+// Write-through helpers; keyed so lookups by base64 hash / URL stay O(1).
+pub fn insert_image(db: &Db, meta: &ImageMetadata) {
+    if let Ok(bytes) = bincode::serialize(meta) {
+        let tree = db.open_tree(IMAGE_TREE).expect("Error opening image tree");
+        let _ = tree.insert(meta.hash.to_base64().as_bytes(), bytes);
+    }
+}
+
+pub fn insert_link(db: &Db, meta: &LinkMetadata) {
+    if let Ok(bytes) = bincode::serialize(meta) {
+        let tree = db.open_tree(LINK_TREE).expect("Error opening link tree");
+        let _ = tree.insert(meta.url.as_bytes(), bytes);
+    }
+}
+
+pub fn insert_allowed_hash(db: &Db, hash: &ImageHash) {
+    let tree = db.open_tree(ALLOWED_HASH_TREE).expect("Error opening allowed hash tree");
+    let _ = tree.insert(hash.to_base64().as_bytes(), &[]);
+}
+
+pub fn insert_allowed_link(db: &Db, url: &str) {
+    let tree = db.open_tree(ALLOWED_LINK_TREE).expect("Error opening allowed link tree");
+    let _ = tree.insert(url.as_bytes(), &[]);
+}
+
+pub fn remove_image(db: &Db, hash: &ImageHash) {
+    let tree = db.open_tree(IMAGE_TREE).expect("Error opening image tree");
+    let _ = tree.remove(hash.to_base64().as_bytes());
+}
+
+pub fn remove_link(db: &Db, url: &str) {
+    let tree = db.open_tree(LINK_TREE).expect("Error opening link tree");
+    let _ = tree.remove(url.as_bytes());
+}
+
+pub fn remove_allowed_hash(db: &Db, hash: &ImageHash) {
+    let tree = db.open_tree(ALLOWED_HASH_TREE).expect("Error opening allowed hash tree");
+    let _ = tree.remove(hash.to_base64().as_bytes());
+}
+
+pub fn remove_allowed_link(db: &Db, url: &str) {
+    let tree = db.open_tree(ALLOWED_LINK_TREE).expect("Error opening allowed link tree");
+    let _ = tree.remove(url.as_bytes());
+}
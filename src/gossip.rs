@@ -0,0 +1,153 @@
+use std::collections::{HashSet, VecDeque};
+use std::env;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::net::UdpSocket;
+
+// Leading wire-format byte so future fields don't break older peers.
+const VERSION: u8 = 1;
+// Hop budget a freshly-originated record starts with, bounding flooding.
+const DEFAULT_TTL: u8 = 3;
+// Upper bound on remembered record identities, so dedup state can't leak.
+const SEEN_CAPACITY: usize = 4096;
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub enum Kind {
+    Image,
+    Link,
+}
+
+// A single repost observation shared with peers: a base64 hash (images) or URL
+// (links) plus enough context to rebuild the local cache entry.
+#[derive(Serialize, Deserialize)]
+pub struct Record {
+    pub kind: Kind,
+    pub key: String,
+    // hash-algorithm identity for images; empty for links
+    pub algo: String,
+    pub timestamp: DateTime<Utc>,
+    pub msg_link: String,
+    pub user_id: u64,
+}
+
+impl Record {
+    // Per-item dedup identity: a single message with several attachments/links
+    // yields several records sharing one `msg_link`, so we key on the item.
+    pub fn dedup_key(&self) -> String {
+        let kind = match self.kind {
+            Kind::Image => 'i',
+            Kind::Link => 'l',
+        };
+        format!("{}:{}", kind, self.key)
+    }
+}
+
+// A bounded insertion-ordered set of record identities, evicting the oldest
+// once `cap` is reached so dedup state stays bounded like the other caches.
+struct SeenSet {
+    set: HashSet<String>,
+    order: VecDeque<String>,
+    cap: usize,
+}
+
+impl SeenSet {
+    fn new(cap: usize) -> SeenSet {
+        SeenSet { set: HashSet::new(), order: VecDeque::new(), cap }
+    }
+
+    // Record `key`; returns `true` only the first time it's seen.
+    fn insert(&mut self, key: String) -> bool {
+        if !self.set.insert(key.clone()) {
+            return false;
+        }
+        self.order.push_back(key);
+        if self.order.len() > self.cap {
+            if let Some(oldest) = self.order.pop_front() {
+                self.set.remove(&oldest);
+            }
+        }
+        true
+    }
+}
+
+// Bound UDP socket plus the static peer list to fan records out to.
+pub struct Gossip {
+    socket: Arc<UdpSocket>,
+    peers: Vec<SocketAddr>,
+    local_addr: Option<SocketAddr>,
+    // record identities we've already originated or merged, so self-originated
+    // and duplicate records are dropped explicitly rather than relying on the
+    // hash-distance dedup to happen to catch them.
+    seen: Arc<Mutex<SeenSet>>,
+}
+
+impl Gossip {
+    // Bind the gossip socket if `REPOST_GOSSIP_BIND` is configured; peers come
+    // from the comma-separated `REPOST_GOSSIP_PEERS`.
+    pub async fn from_env() -> Option<Gossip> {
+        let bind = env::var("REPOST_GOSSIP_BIND").ok()?;
+        let socket = UdpSocket::bind(&bind).await.ok()?;
+        let local_addr = socket.local_addr().ok();
+        let peers = env::var("REPOST_GOSSIP_PEERS").ok()
+            .map(|list| list.split(',')
+                .filter_map(|p| p.trim().parse::<SocketAddr>().ok())
+                .collect())
+            .unwrap_or_default();
+        Some(Gossip {
+            socket: Arc::new(socket),
+            peers,
+            local_addr,
+            seen: Arc::new(Mutex::new(SeenSet::new(SEEN_CAPACITY))),
+        })
+    }
+
+    pub fn socket(&self) -> Arc<UdpSocket> {
+        self.socket.clone()
+    }
+
+    pub fn peers(&self) -> Vec<SocketAddr> {
+        self.peers.clone()
+    }
+
+    // Record `record`'s identity as known; returns `true` only the first time.
+    pub fn mark_seen(&self, record: &Record) -> bool {
+        self.seen.lock().unwrap().insert(record.dedup_key())
+    }
+
+    // True if `src` is one of our own bind addresses (loopback self-delivery).
+    pub fn is_self(&self, src: &SocketAddr) -> bool {
+        self.local_addr.map(|addr| addr == *src).unwrap_or(false)
+    }
+
+    // Originate a record with a fresh TTL.
+    pub async fn broadcast(&self, record: &Record) {
+        self.mark_seen(record);
+        send(&self.socket, &self.peers, record, DEFAULT_TTL).await;
+    }
+}
+
+// Serialize `record` into a versioned datagram and send it to every peer.
+pub async fn send(socket: &UdpSocket, peers: &[SocketAddr], record: &Record, ttl: u8) {
+    let mut packet = vec![VERSION, ttl];
+    match bincode::serialize(record) {
+        Ok(mut body) => packet.append(&mut body),
+        Err(_) => return,
+    }
+    for peer in peers {
+        let _ = socket.send_to(&packet, peer).await;
+    }
+}
+
+// Decode a datagram into its remaining TTL and record, dropping anything
+// malformed or from an unknown wire version.
+pub fn decode(packet: &[u8]) -> Option<(u8, Record)> {
+    if packet.len() < 2 || packet[0] != VERSION {
+        return None;
+    }
+    let ttl = packet[1];
+    let record = bincode::deserialize::<Record>(&packet[2..]).ok()?;
+    Some((ttl, record))
+}
@@ -1,28 +1,43 @@
 use std::collections::HashSet;
 use std::env;
+use std::sync::Arc;
 
 use chrono::{DateTime, Utc};
 use image;
-use img_hash::{HasherConfig, ImageHash};
+use img_hash::{HashAlg, Hasher, HasherConfig, ImageHash};
+use serde::{Deserialize, Serialize};
 use serenity::async_trait;
 use serenity::client::{Client, Context, EventHandler};
 use serenity::framework::standard::StandardFramework;
 use serenity::model::channel::{Attachment, Message};
+use serenity::model::id::UserId;
 use serenity::model::user::User;
-use serenity::prelude::TypeMapKey;
+use serenity::prelude::{RwLock, TypeMap, TypeMapKey};
 use serenity::utils::MessageBuilder;
 
+mod cache;
+mod commands;
+mod db;
+mod gossip;
+mod source;
+
+use cache::{RepostCache, Timestamped};
+
 struct Handler;
 
-#[derive(PartialEq, Eq, Hash)]
+#[derive(PartialEq, Eq, Hash, Clone, Serialize, Deserialize)]
 struct ImageMetadata {
+    #[serde(with = "hash_base64")]
     hash: ImageHash,
+    // algorithm identity so we never compare hashes produced by different
+    // algorithms/sizes after a config change
+    algo: String,
     timestamp: DateTime<Utc>,
     user: User,
     msg_link: String,
 }
 
-#[derive(PartialEq, Eq, Hash)]
+#[derive(PartialEq, Eq, Hash, Clone, Serialize, Deserialize)]
 struct LinkMetadata {
     url: String,
     timestamp: DateTime<Utc>,
@@ -30,9 +45,43 @@ struct LinkMetadata {
     msg_link: String,
 }
 
+impl Timestamped for ImageMetadata {
+    fn timestamp(&self) -> DateTime<Utc> {
+        self.timestamp
+    }
+}
+
+impl Timestamped for LinkMetadata {
+    fn timestamp(&self) -> DateTime<Utc> {
+        self.timestamp
+    }
+}
+
+// `ImageHash` isn't `serde`-aware, but it round-trips through base64 already.
+mod hash_base64 {
+    use img_hash::ImageHash;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(hash: &ImageHash, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&hash.to_base64())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<ImageHash, D::Error> {
+        let b64 = String::deserialize(deserializer)?;
+        ImageHash::from_base64(&b64).map_err(|e| serde::de::Error::custom(format!("{:?}", e)))
+    }
+}
+
 struct HashMetadata {
     hash: ImageHash,
     source_type: String,
+    bytes: Vec<u8>,
+}
+
+struct PreparedHasher;
+
+impl TypeMapKey for PreparedHasher {
+    type Value = Arc<Hasher>;
 }
 
 struct HashCache;
@@ -46,14 +95,51 @@ struct AllowedHashes;
 struct Config {
     cache_limit: u64,
     ignored_types: Vec<String>,
+    prefix: String,
+    threshold: u32,
+    attachment_threshold: Option<u32>,
+    embedded_threshold: Option<u32>,
+    max_age_days: Option<i64>,
+    hash_alg: HashAlg,
+    hash_size: u32,
+    preproc_dct: bool,
+}
+
+impl Config {
+    // A stable string identifying the hash algorithm/size/preproc in use, so
+    // cached entries from an incompatible config are never matched against.
+    fn hash_identity(&self) -> String {
+        format!("{:?}:{}x{}:dct={}", self.hash_alg, self.hash_size, self.hash_size, self.preproc_dct)
+    }
+
+    fn build_hasher(&self) -> Hasher {
+        let mut config = HasherConfig::new()
+            .hash_alg(self.hash_alg)
+            .hash_size(self.hash_size, self.hash_size);
+        if self.preproc_dct {
+            config = config.preproc_dct();
+        }
+        config.to_hasher()
+    }
+
+    // Distance tolerated for a given source; attachments and embeds can be
+    // tuned independently via env, otherwise they track the base `~threshold`
+    // (which the runtime command mutates).
+    fn threshold_for(&self, source_type: &str) -> u32 {
+        match source_type {
+            "attachment" => self.attachment_threshold.unwrap_or(self.threshold),
+            "embedded" => self.embedded_threshold.unwrap_or(self.threshold),
+            _ => self.threshold,
+        }
+    }
 }
 
 impl TypeMapKey for HashCache {
-    type Value = HashSet<ImageMetadata>;
+    type Value = RepostCache<ImageMetadata>;
 }
 
 impl TypeMapKey for LinkCache {
-    type Value = HashSet<LinkMetadata>;
+    type Value = RepostCache<LinkMetadata>;
 }
 
 impl TypeMapKey for Config {
@@ -68,9 +154,31 @@ impl TypeMapKey for AllowedHashes {
     type Value = HashSet<ImageHash>;
 }
 
+struct Database;
+
+impl TypeMapKey for Database {
+    type Value = sled::Db;
+}
+
+struct Stats;
+
+impl TypeMapKey for Stats {
+    // running total of reposts caught since startup
+    type Value = u64;
+}
+
+struct GossipHandle;
+
+impl TypeMapKey for GossipHandle {
+    type Value = Arc<gossip::Gossip>;
+}
+
 #[tokio::main]
 async fn main() {
-    let framework = StandardFramework::new();
+    let config = read_config();
+    let framework = StandardFramework::new()
+        .configure(|c| c.prefix(config.prefix.clone()))
+        .group(&commands::REPOST_GROUP);
     // Login with a bot token from the environment
     let token = env::var("REPOST_DISCORD_TOKEN").expect("Expected a REPOST_DISCORD_TOKEN env var");
     let mut client = Client::builder(token)
@@ -80,12 +188,28 @@ async fn main() {
         .expect("Error creating client");
     {
         let mut data = client.data.write().await;
-        let config = read_config();
+        let database = db::open();
+        let cache_limit = config.cache_limit;
+        let max_age_days = config.max_age_days;
+        let hasher = config.build_hasher();
+        data.insert::<HashCache>(RepostCache::new(db::load_images(&database), cache_limit, max_age_days));
+        data.insert::<LinkCache>(RepostCache::new(db::load_links(&database), cache_limit, max_age_days));
+        data.insert::<AllowedHashes>(db::load_allowed_hashes(&database));
+        data.insert::<AllowedLinks>(db::load_allowed_links(&database));
         data.insert::<Config>(config);
-        data.insert::<HashCache>(HashSet::with_capacity(read_config().cache_limit as usize));
-        data.insert::<LinkCache>(HashSet::with_capacity(read_config().cache_limit as usize));
-        data.insert::<AllowedHashes>(HashSet::with_capacity(read_config().cache_limit as usize));
-        data.insert::<AllowedLinks>(HashSet::with_capacity(read_config().cache_limit as usize));
+        data.insert::<Database>(database);
+        data.insert::<Stats>(0);
+        data.insert::<PreparedHasher>(Arc::new(hasher));
+    }
+    // optionally join the gossip mesh so peers share repost memory
+    if let Some(gossip) = gossip::Gossip::from_env().await {
+        let gossip = Arc::new(gossip);
+        let data = client.data.clone();
+        {
+            let mut locked = client.data.write().await;
+            locked.insert::<GossipHandle>(gossip.clone());
+        }
+        tokio::spawn(gossip_receiver(data, gossip));
     }
     // start listening for events by starting a single shard
     if let Err(why) = client.start().await {
@@ -97,23 +221,163 @@ fn read_config() -> Config {
     let cache_limit = env::var("REPOST_CACHE_LIMIT").expect("Expected a REPOST_CACHE_LIMIT env var").parse::<u64>().unwrap();
     let ignored_str = env::var("REPOST_IGNORED_TYPES").expect("Expected a REPOST_IGNORED_TYPES env var").parse::<String>().unwrap();
     let ignored_types: Vec<String> = ignored_str.split(",").map(|str| String::from(str)).collect();
-    let config = Config { cache_limit, ignored_types };
+    // ignore an empty prefix — `starts_with("")` would swallow every message
+    let prefix = env::var("REPOST_PREFIX").ok().filter(|p| !p.is_empty()).unwrap_or_else(|| String::from("~"));
+    let threshold = env::var("REPOST_MATCH_DISTANCE").ok().and_then(|s| s.parse::<u32>().ok()).unwrap_or(2);
+    let attachment_threshold = env::var("REPOST_MATCH_DISTANCE_ATTACHMENT").ok().and_then(|s| s.parse::<u32>().ok());
+    let embedded_threshold = env::var("REPOST_MATCH_DISTANCE_EMBEDDED").ok().and_then(|s| s.parse::<u32>().ok());
+    let max_age_days = env::var("REPOST_MAX_AGE_DAYS").ok().and_then(|s| s.parse::<i64>().ok());
+    let hash_alg_name = env::var("REPOST_HASH_ALG").ok();
+    let hash_alg = hash_alg_name.as_deref().map(parse_hash_alg).unwrap_or(HashAlg::Gradient);
+    let hash_size = env::var("REPOST_HASH_SIZE").ok().and_then(|s| s.parse::<u32>().ok()).unwrap_or(8);
+    let preproc_dct = hash_alg_name.map(|s| s.eq_ignore_ascii_case("dct")).unwrap_or(false)
+        || env::var("REPOST_HASH_DCT").ok().map(|s| s == "1" || s.eq_ignore_ascii_case("true")).unwrap_or(false);
+    let config = Config {
+        cache_limit,
+        ignored_types,
+        prefix,
+        threshold,
+        attachment_threshold,
+        embedded_threshold,
+        max_age_days,
+        hash_alg,
+        hash_size,
+        preproc_dct,
+    };
     config
 }
 
+fn parse_hash_alg(name: &str) -> HashAlg {
+    match name.to_ascii_lowercase().as_str() {
+        "mean" => HashAlg::Mean,
+        "gradient" => HashAlg::Gradient,
+        "vertgradient" => HashAlg::VertGradient,
+        "doublegradient" => HashAlg::DoubleGradient,
+        "blockhash" => HashAlg::Blockhash,
+        // "dct" selects the DCT-preprocessed mean hash; see REPOST_HASH_DCT too
+        "dct" => HashAlg::Mean,
+        _ => HashAlg::Gradient,
+    }
+}
+
+// Receive gossiped records, merge novel ones into the local caches, and
+// re-gossip them to peers until their hop budget runs out.
+async fn gossip_receiver(data: Arc<RwLock<TypeMap>>, gossip: Arc<gossip::Gossip>) {
+    let socket = gossip.socket();
+    let peers = gossip.peers();
+    let mut buf = vec![0u8; 64 * 1024];
+    loop {
+        let (len, src) = match socket.recv_from(&mut buf).await {
+            Ok(pair) => pair,
+            Err(_) => continue,
+        };
+        // drop packets we delivered to ourselves
+        if gossip.is_self(&src) {
+            continue;
+        }
+        let (ttl, record) = match gossip::decode(&buf[..len]) {
+            Some(decoded) => decoded,
+            None => continue, // malformed or unknown wire version
+        };
+        // drop self-originated / already-seen records explicitly
+        if !gossip.mark_seen(&record) {
+            continue;
+        }
+        let novel = {
+            let mut store = data.write().await;
+            let (threshold, identity) = store.get::<Config>()
+                .map(|c| (c.threshold_for("embedded"), c.hash_identity()))
+                .unwrap_or((2, String::new()));
+            let database = store.get::<Database>().unwrap().clone();
+            match record.kind {
+                gossip::Kind::Image => merge_image(&mut store, &database, &record, threshold, &identity),
+                gossip::Kind::Link => merge_link(&mut store, &database, &record),
+            }
+        };
+        if novel && ttl > 0 {
+            gossip::send(&socket, &peers, &record, ttl - 1).await;
+        }
+    }
+}
+
+// Build a minimally-populated `User` for a gossiped record; only the id is
+// carried on the wire, which is all `MessageBuilder::mention` needs.
+fn gossip_user(user_id: u64) -> User {
+    let mut user = User::default();
+    user.id = UserId(user_id);
+    user
+}
+
+fn merge_image(store: &mut TypeMap, database: &sled::Db, record: &gossip::Record, threshold: u32, identity: &str) -> bool {
+    // never fold in hashes produced by a different algorithm/size
+    if record.algo != identity {
+        return false;
+    }
+    let hash = match ImageHash::from_base64(&record.key) {
+        Ok(hash) => hash,
+        Err(_) => return false,
+    };
+    let cache = store.get_mut::<HashCache>().unwrap();
+    if cache.find(|i| i.algo == record.algo && hash.dist(&i.hash) < threshold).is_some() {
+        return false;
+    }
+    let metadata = ImageMetadata {
+        hash,
+        algo: record.algo.clone(),
+        timestamp: record.timestamp,
+        user: gossip_user(record.user_id),
+        msg_link: record.msg_link.clone(),
+    };
+    db::insert_image(database, &metadata);
+    for evicted in cache.insert(metadata) {
+        db::remove_image(database, &evicted.hash);
+    }
+    true
+}
+
+fn merge_link(store: &mut TypeMap, database: &sled::Db, record: &gossip::Record) -> bool {
+    let cache = store.get_mut::<LinkCache>().unwrap();
+    if cache.find(|l| l.url == record.key).is_some() {
+        return false;
+    }
+    let metadata = LinkMetadata {
+        url: record.key.clone(),
+        timestamp: record.timestamp,
+        user: gossip_user(record.user_id),
+        msg_link: record.msg_link.clone(),
+    };
+    db::insert_link(database, &metadata);
+    for evicted in cache.insert(metadata) {
+        db::remove_link(database, &evicted.url);
+    }
+    true
+}
+
 #[async_trait]
 impl EventHandler for Handler {
     async fn message(&self, context: Context, msg: Message) {
         if msg.author.bot { return; }
-        if msg.content.contains("--allow") {
-            set_allowed(&context, &msg).await;
-            return;
-        }
         let mut data = context.data.write().await;
         let config: &Config = data.get::<Config>().unwrap();
+        // commands are handled by the framework, not the detector
+        if msg.content.starts_with(&config.prefix) {
+            return;
+        }
         if is_ignored_type(&msg, &config).await {
             return;
         }
+        let hash_identity = config.hash_identity();
+        let attachment_threshold = config.threshold_for("attachment");
+        let embedded_threshold = config.threshold_for("embedded");
+        let mut reposts_found: u64 = 0;
+        // gossip broadcasts and reverse-image lookups are deferred until after
+        // the data lock is released so message processing isn't serialized
+        // behind them; image downloads and repost replies still run under it
+        let mut pending_gossip: Vec<gossip::Record> = Vec::new();
+        let mut pending_sources: Vec<Vec<u8>> = Vec::new();
+        let database: sled::Db = data.get::<Database>().unwrap().clone();
+        let gossip_handle: Option<Arc<gossip::Gossip>> = data.get::<GossipHandle>().cloned();
+        let hasher: Arc<Hasher> = data.get::<PreparedHasher>().unwrap().clone();
         let allowed_links: HashSet<String> = data.get::<AllowedLinks>().unwrap().clone();
         let attachments: Vec<&Attachment> = msg.attachments.iter()
             .map(|a| a)
@@ -127,7 +391,10 @@ impl EventHandler for Handler {
         if attachments.is_empty() && embedded.is_empty() {
             return;
         }
-        let link_cache: &mut HashSet<LinkMetadata> = &mut data.get_mut::<LinkCache>().unwrap();
+        let link_cache: &mut RepostCache<LinkMetadata> = data.get_mut::<LinkCache>().unwrap();
+        for expired in link_cache.prune() {
+            db::remove_link(&database, &expired.url);
+        }
         let url_matches: HashSet<String> = msg.embeds.iter()
             .map(|e| e.url.clone())
             .filter(|e| e.is_some())
@@ -135,8 +402,9 @@ impl EventHandler for Handler {
             .filter(|str| !allowed_links.contains(&*str.to_string()))
             .collect();
         for url in url_matches {
-            let link = link_cache.iter().find(|l| l.url == url);
+            let link = link_cache.find(|l| l.url == url);
             if link.is_some() {
+                reposts_found += 1;
                 let utc_now: DateTime<Utc> = Utc::now();
                 let days_between = utc_now.signed_duration_since(link.unwrap().timestamp).num_days();
                 let mut days_between_str = String::from(" posted this ");
@@ -159,40 +427,75 @@ impl EventHandler for Handler {
                 let user = msg.author.clone();
                 let msg_link = msg.link().clone();
                 let timestamp = msg.timestamp;
-                link_cache.insert(LinkMetadata { url, timestamp, user, msg_link });
+                let metadata = LinkMetadata { url, timestamp, user, msg_link };
+                db::insert_link(&database, &metadata);
+                pending_gossip.push(gossip::Record {
+                    kind: gossip::Kind::Link,
+                    key: metadata.url.clone(),
+                    algo: String::new(),
+                    timestamp: metadata.timestamp,
+                    msg_link: metadata.msg_link.clone(),
+                    user_id: metadata.user.id.0,
+                });
+                for evicted in link_cache.insert(metadata) {
+                    db::remove_link(&database, &evicted.url);
+                }
             }
         }
         let mut hashes: Vec<HashMetadata> = Vec::new();
         let allowed_hashes: HashSet<ImageHash> = data.get::<AllowedHashes>().unwrap().clone();
         for url in embedded {
-            if let Some(image_hash) = get_embedded_hash(url).await {
+            if let Some((image_hash, bytes)) = get_embedded_hash(url, &hasher).await {
                 if !allowed_hashes.contains(&image_hash) {
-                    hashes.push(HashMetadata { hash: image_hash, source_type: String::from("embedded") });
+                    hashes.push(HashMetadata { hash: image_hash, source_type: String::from("embedded"), bytes });
                 }
             }
         }
         for attachment in attachments {
-            if let Some(image_hash) = get_attachment_hash(attachment.clone()).await {
+            if let Some((image_hash, bytes)) = get_attachment_hash(attachment.clone(), &hasher).await {
                 if !allowed_hashes.contains(&image_hash) {
-                    hashes.push(HashMetadata { hash: image_hash, source_type: String::from("attachment") });
+                    hashes.push(HashMetadata { hash: image_hash, source_type: String::from("attachment"), bytes });
                 }
             }
         }
         let mut result: Option<&ImageMetadata>;
-        let metadata_cache: &mut HashSet<ImageMetadata> = &mut data.get_mut::<HashCache>().unwrap();
+        let metadata_cache: &mut RepostCache<ImageMetadata> = data.get_mut::<HashCache>().unwrap();
+        for expired in metadata_cache.prune() {
+            db::remove_image(&database, &expired.hash);
+        }
         for meta_hash in hashes {
             let hash = meta_hash.hash;
             let source_type = meta_hash.source_type;
-            result = metadata_cache.iter()
-                .find(|i| hash.dist(&i.hash) < 2);
+            let bytes = meta_hash.bytes;
+            let threshold = if source_type == "attachment" { attachment_threshold } else { embedded_threshold };
+            // only compare against cache entries produced by the same algorithm
+            result = metadata_cache.find(|i| i.algo == hash_identity && hash.dist(&i.hash) < threshold);
             if result.is_none() {
+                // Not a local repost — defer a reverse-image lookup so we can
+                // still surface cross-server / external originals once the lock
+                // is released.
+                pending_sources.push(bytes);
                 let user = msg.author.clone();
                 let msg_link = msg.link().clone();
                 let timestamp = msg.timestamp;
-                metadata_cache.insert(ImageMetadata { hash, timestamp, user, msg_link });
+                let algo = hash_identity.clone();
+                let metadata = ImageMetadata { hash, algo, timestamp, user, msg_link };
+                db::insert_image(&database, &metadata);
+                pending_gossip.push(gossip::Record {
+                    kind: gossip::Kind::Image,
+                    key: metadata.hash.to_base64(),
+                    algo: metadata.algo.clone(),
+                    timestamp: metadata.timestamp,
+                    msg_link: metadata.msg_link.clone(),
+                    user_id: metadata.user.id.0,
+                });
+                for evicted in metadata_cache.insert(metadata) {
+                    db::remove_image(&database, &evicted.hash);
+                }
             } else {
                 // dont send message if embedded, message already sent earlier if link
-                if source_type == "embedded" { return; }
+                if source_type == "embedded" { continue; }
+                reposts_found += 1;
                 let utc_now: DateTime<Utc> = Utc::now();
                 let days_between = utc_now.signed_duration_since(result.unwrap().timestamp).num_days();
                 let mut days_between_str = String::from(" posted this ");
@@ -212,57 +515,134 @@ impl EventHandler for Handler {
                 }
             }
         }
+        if reposts_found > 0 {
+            if let Some(stats) = data.get_mut::<Stats>() {
+                *stats += reposts_found;
+            }
+        }
+        // release the data lock before the gossip and reverse-image calls
+        drop(data);
+        if let Some(gossip) = &gossip_handle {
+            for record in &pending_gossip {
+                gossip.broadcast(record).await;
+            }
+        }
+        // only surface external sources when we didn't already report a repost
+        if reposts_found == 0 {
+            for bytes in pending_sources {
+                if let Some(source) = source::lookup(&bytes).await {
+                    let msg_content = MessageBuilder::new()
+                        .push("Looks like the original source (")
+                        .push(format!("{:.0}% match on {}", source.similarity, source.site))
+                        .push("): ")
+                        .push(source.url)
+                        .build();
+                    if let Err(e) = msg.reply_mention(&context.http, msg_content).await {
+                        println!("{}", e);
+                    }
+                }
+            }
+        }
     }
 }
 
-async fn get_embedded_hash(url: String) -> Option<ImageHash> {
+async fn get_embedded_hash(url: String, hasher: &Hasher) -> Option<(ImageHash, Vec<u8>)> {
     if let Ok(resp) = reqwest::get(&url).await {
         if let Ok(img_bytes) = resp.bytes().await {
             if let Ok(img) = &image::load_from_memory(img_bytes.as_ref()) {
-                let image_hash = HasherConfig::new()
-                    .to_hasher()
-                    .hash_image(img);
-                return Some(image_hash);
+                let image_hash = hasher.hash_image(img);
+                return Some((image_hash, img_bytes.to_vec()));
             }
         }
     }
     None
 }
 
-async fn get_attachment_hash(attachment: Attachment) -> Option<ImageHash> {
+async fn get_attachment_hash(attachment: Attachment, hasher: &Hasher) -> Option<(ImageHash, Vec<u8>)> {
     if let Ok(img) = attachment.download().await {
-        if let Ok(img) = &image::load_from_memory(img.as_ref()) {
-            return Some(HasherConfig::new()
-                .to_hasher()
-                .hash_image(img));
+        if let Ok(loaded) = &image::load_from_memory(img.as_ref()) {
+            let image_hash = hasher.hash_image(loaded);
+            return Some((image_hash, img));
         }
     }
     None
 }
 
-async fn set_allowed(context: &Context, msg: &Message) {
+// Allow-list every attachment/embed of `target`, writing through to disk.
+async fn set_allowed(context: &Context, target: &Message) {
     let mut data = context.data.write().await;
-    if !msg.embeds.is_empty() {
+    let database: sled::Db = data.get::<Database>().unwrap().clone();
+    let hasher: Arc<Hasher> = data.get::<PreparedHasher>().unwrap().clone();
+    if !target.embeds.is_empty() {
         let allowed_links: &mut HashSet<String> = data.get_mut::<AllowedLinks>().unwrap();
-        let url_matches: HashSet<String> = msg.embeds.iter()
+        let url_matches: HashSet<String> = target.embeds.iter()
             .map(|e| e.url.clone())
             .filter(|e| e.is_some())
             .map(|e| e.unwrap())
             .collect();
         for url in url_matches {
-            if let Ok(url) = url.parse() {
+            if let Ok(url) = url.parse::<String>() {
+                db::insert_allowed_link(&database, &url);
                 allowed_links.insert(url);
             }
         }
     }
-    let allowed_hashes: &mut HashSet<ImageHash> = data.get_mut::<AllowedHashes>().unwrap();
-    for attachment in &msg.attachments {
-        if let Some(img_hash) = get_attachment_hash(attachment.clone()).await {
-            allowed_hashes.insert(img_hash);
+    for attachment in &target.attachments {
+        if let Some((img_hash, _)) = get_attachment_hash(attachment.clone(), &hasher).await {
+            db::insert_allowed_hash(&database, &img_hash);
+            data.get_mut::<AllowedHashes>().unwrap().insert(img_hash);
+        }
+    }
+}
+
+// Drop every attachment/embed of `target` back off the allow-lists.
+async fn unset_allowed(context: &Context, target: &Message) {
+    let mut data = context.data.write().await;
+    let database: sled::Db = data.get::<Database>().unwrap().clone();
+    let hasher: Arc<Hasher> = data.get::<PreparedHasher>().unwrap().clone();
+    if !target.embeds.is_empty() {
+        let allowed_links: &mut HashSet<String> = data.get_mut::<AllowedLinks>().unwrap();
+        for url in target.embeds.iter().filter_map(|e| e.url.clone()) {
+            db::remove_allowed_link(&database, &url);
+            allowed_links.remove(&url);
+        }
+    }
+    for attachment in &target.attachments {
+        if let Some((img_hash, _)) = get_attachment_hash(attachment.clone(), &hasher).await {
+            db::remove_allowed_hash(&database, &img_hash);
+            data.get_mut::<AllowedHashes>().unwrap().remove(&img_hash);
         }
     }
 }
 
+// Evict any cached image/link that was first seen at `msg_link`.
+async fn forget_entry(context: &Context, msg_link: &str) -> bool {
+    let mut data = context.data.write().await;
+    let database: sled::Db = data.get::<Database>().unwrap().clone();
+    let mut forgotten = false;
+    let image_cache: &mut RepostCache<ImageMetadata> = data.get_mut::<HashCache>().unwrap();
+    let stale_hashes: Vec<ImageHash> = image_cache.iter()
+        .filter(|i| i.msg_link == msg_link)
+        .map(|i| i.hash.clone())
+        .collect();
+    for hash in &stale_hashes {
+        db::remove_image(&database, hash);
+        image_cache.retain(|i| &i.hash != hash);
+        forgotten = true;
+    }
+    let link_cache: &mut RepostCache<LinkMetadata> = data.get_mut::<LinkCache>().unwrap();
+    let stale_urls: Vec<String> = link_cache.iter()
+        .filter(|l| l.msg_link == msg_link)
+        .map(|l| l.url.clone())
+        .collect();
+    for url in &stale_urls {
+        db::remove_link(&database, url);
+        link_cache.retain(|l| &l.url != url);
+        forgotten = true;
+    }
+    forgotten
+}
+
 async fn is_ignored_type(msg: &Message, config: &Config) -> bool {
     let ignore_attached = config.ignored_types.contains(&String::from("attachment"));
     let ignore_links = config.ignored_types.contains(&String::from("links"));
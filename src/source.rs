@@ -0,0 +1,66 @@
+use std::cmp::Ordering;
+use std::env;
+
+use serde::Deserialize;
+
+// A confident original-source match returned by the reverse-image backend.
+pub struct SourceMatch {
+    pub url: String,
+    pub site: String,
+    pub similarity: f32,
+}
+
+// Query the configured SauceNAO/IQDB-style backend for the original source of
+// an image, using the bytes we already downloaded to hash it. Returns `None`
+// when no backend is configured or nothing clears the confidence floor.
+// `REPOST_SAUCE_ENDPOINT`/`REPOST_SAUCE_KEY` configure the backend and
+// `REPOST_SAUCE_MIN_SIMILARITY` (percent, default 80) the floor.
+pub async fn lookup(bytes: &[u8]) -> Option<SourceMatch> {
+    let endpoint = env::var("REPOST_SAUCE_ENDPOINT").ok()?;
+    let key = env::var("REPOST_SAUCE_KEY").ok()?;
+    let min_similarity = env::var("REPOST_SAUCE_MIN_SIMILARITY").ok()
+        .and_then(|s| s.parse::<f32>().ok())
+        .unwrap_or(80.0);
+    let part = reqwest::multipart::Part::bytes(bytes.to_vec()).file_name("image");
+    let form = reqwest::multipart::Form::new().part("file", part);
+    let resp = reqwest::Client::new()
+        .post(&endpoint)
+        .query(&[("api_key", key.as_str()), ("output_type", "2")])
+        .multipart(form)
+        .send()
+        .await
+        .ok()?;
+    let body: SauceResponse = resp.json().await.ok()?;
+    body.results.into_iter()
+        .filter_map(|result| {
+            let similarity = result.header.similarity.parse::<f32>().ok()?;
+            let url = result.data.ext_urls.into_iter().next()?;
+            Some(SourceMatch { url, site: result.header.index_name, similarity })
+        })
+        .filter(|m| m.similarity >= min_similarity)
+        .max_by(|a, b| a.similarity.partial_cmp(&b.similarity).unwrap_or(Ordering::Equal))
+}
+
+#[derive(Deserialize)]
+struct SauceResponse {
+    #[serde(default)]
+    results: Vec<SauceResult>,
+}
+
+#[derive(Deserialize)]
+struct SauceResult {
+    header: SauceHeader,
+    data: SauceData,
+}
+
+#[derive(Deserialize)]
+struct SauceHeader {
+    similarity: String,
+    index_name: String,
+}
+
+#[derive(Deserialize)]
+struct SauceData {
+    #[serde(default)]
+    ext_urls: Vec<String>,
+}